@@ -23,15 +23,56 @@ use consensus_common::{
 	ImportResult, SelectChain,
 };
 use consensus_common::import_queue::{BasicQueue, CacheKeyId, Verifier, BoxBlockImport};
-use sr_primitives::traits::{Block as BlockT};
+use sr_primitives::traits::{Block as BlockT, Header as HeaderT};
 use sr_primitives::Justification;
 use parking_lot::Mutex;
 use futures::prelude::*;
+use futures::channel::mpsc;
+use futures_timer::Delay;
 use transaction_pool::txpool::{self, Pool as TransactionPool};
+use manual_seal::Error;
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Configures how many ready transactions `run_instant_seal` waits for, and for how long, before
+/// authoring a block, trading off block latency against block count under sustained transaction load.
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+	/// author a block as soon as this many transactions are ready, without waiting for `max_wait`.
+	pub max_transactions: usize,
+	/// author a block at most this long after the first ready transaction of the batch arrived,
+	/// even if `max_transactions` hasn't been reached yet.
+	pub max_wait: Duration,
+}
+
+impl Default for BatchConfig {
+	fn default() -> Self {
+		BatchConfig {
+			max_transactions: 128,
+			max_wait: Duration::from_millis(500),
+		}
+	}
+}
+
+/// A block proposed by the instant-seal authorship task.
+///
+/// Sent on the notification channel returned by [`run_instant_seal`] as soon as
+/// `Proposer::propose` succeeds, ahead of (and regardless of the outcome of) `import_block` —
+/// this gives observers a hook to inspect and measure authorship latency without having to poll
+/// the chain for the block to appear.
+#[derive(Debug)]
+pub struct ProposedBlock<B: BlockT> {
+	/// hash of the proposed block.
+	pub hash: B::Hash,
+	/// header of the proposed block.
+	pub header: B::Header,
+	/// number of extrinsics in the proposed block's body.
+	pub body_len: usize,
+	/// time it took `Proposer::propose` to return this block.
+	pub duration: Duration,
+}
 
 /// The synchronous block-import worker of the engine.
 pub struct InstantSealBlockImport<I> {
@@ -63,8 +104,13 @@ impl<B: BlockT, I: BlockImport<B>> BlockImport<B> for InstantSealBlockImport<I>
 	}
 }
 
-/// The verifier for the instant seal engine; instantly finalizes.
-struct InstantSealVerifier;
+/// The verifier for the instant seal engine.
+///
+/// `instant_finalize` controls whether blocks it verifies are immediately marked final, or left
+/// for an explicit finalization step (e.g. `engine_finalizeBlock`) to decide.
+struct InstantSealVerifier {
+	instant_finalize: bool,
+}
 
 impl<B: BlockT> Verifier<B> for InstantSealVerifier {
 	fn verify(
@@ -80,7 +126,7 @@ impl<B: BlockT> Verifier<B> for InstantSealVerifier {
 			justification,
 			post_digests: Vec::new(),
 			body,
-			finalized: true,
+			finalized: self.instant_finalize,
 			auxiliary: Vec::new(),
 			fork_choice: ForkChoiceStrategy::LongestChain,
 			allow_missing_state: false,
@@ -91,10 +137,10 @@ impl<B: BlockT> Verifier<B> for InstantSealVerifier {
 }
 
 /// Instantiate the import queue for the instant-seal consensus engine.
-pub fn import_queue<B: BlockT>(block_import: BoxBlockImport<B>) -> BasicQueue<B>
+pub fn import_queue<B: BlockT>(block_import: BoxBlockImport<B>, instant_finalize: bool) -> BasicQueue<B>
 {
 	BasicQueue::new(
-		InstantSealVerifier,
+		InstantSealVerifier { instant_finalize },
 		block_import,
 		None,
 		None,
@@ -102,12 +148,51 @@ pub fn import_queue<B: BlockT>(block_import: BoxBlockImport<B>) -> BasicQueue<B>
 }
 
 /// Creates the background authorship task for the instant seal engine.
-pub async fn run_instant_seal<B, E, A, C>(
+///
+/// Rather than authoring a block on every single `import_notification_stream` event, ready
+/// transactions are batched per `batch_config`: a block is proposed as soon as
+/// `batch_config.max_transactions` are ready, or after `batch_config.max_wait` has elapsed since
+/// the first transaction of the batch arrived, whichever comes first. This sweeps up all ready
+/// extrinsics in one go and keeps busy dev/CI chains from producing a flood of near-empty blocks.
+///
+/// Returns a [`ProposedBlock`] notification stream alongside the authorship future; the caller is
+/// responsible for spawning the future, e.g. `let (proposals, task) = run_instant_seal(...);
+/// executor.spawn(task);`.
+pub fn run_instant_seal<B, E, A, C>(
+	block_import: BoxBlockImport<B>,
+	env: E,
+	pool: Arc<TransactionPool<A>>,
+	select_chain: C,
+	inherent_data_providers: inherents::InherentDataProviders,
+	// whether newly authored blocks should be marked final immediately, or left for an explicit
+	// finalization step to decide.
+	instant_finalize: bool,
+	batch_config: BatchConfig,
+) -> (mpsc::UnboundedReceiver<ProposedBlock<B>>, impl Future<Output = ()>)
+	where
+		B: BlockT + 'static,
+		E: Environment<B> + 'static,
+		A: txpool::ChainApi + 'static,
+		C: SelectChain<B> + 'static,
+{
+	let (proposed_sender, proposed_receiver) = mpsc::unbounded();
+	let task = run_instant_seal_inner(
+		block_import, env, pool, select_chain, inherent_data_providers, instant_finalize,
+		batch_config, proposed_sender,
+	);
+
+	(proposed_receiver, task)
+}
+
+async fn run_instant_seal_inner<B, E, A, C>(
 	block_import: BoxBlockImport<B>,
 	env: E,
 	pool: Arc<TransactionPool<A>>,
 	select_chain: C,
 	inherent_data_providers: inherents::InherentDataProviders,
+	instant_finalize: bool,
+	batch_config: BatchConfig,
+	proposed_sender: mpsc::UnboundedSender<ProposedBlock<B>>,
 )
 	where
 		B: BlockT + 'static,
@@ -117,72 +202,107 @@ pub async fn run_instant_seal<B, E, A, C>(
 {
 	let block_import = Arc::new(Mutex::new(block_import));
 	let env = Arc::new(Mutex::new(env));
-	let select_chain = Arc::new(select_chain);
-	let inherent_data_providers = Arc::new(inherent_data_providers);
-	let moved_pool = pool.clone();
-
-	// propose a new block everytime a transaction is imported
-	pool.import_notification_stream()
-		.for_each(move |_| {
-			let select_chain = select_chain.clone();
-			let env = env.clone();
-			let inherent_data_providers = inherent_data_providers.clone();
-			let block_import = block_import.clone();
-			let moved_pool = moved_pool.clone();
-
-			async move {
-				// prev
-				if moved_pool.status().ready == 0 {
-					return
+	let mut notifications = pool.import_notification_stream();
+	let mut batch_started_at: Option<Instant> = None;
+
+	loop {
+		let timeout = batch_started_at
+			.map(|started| batch_config.max_wait.saturating_sub(started.elapsed()))
+			.unwrap_or(Duration::from_secs(3600));
+
+		futures::select! {
+			notification = notifications.next().fuse() => match notification {
+				Some(_) => {
+					batch_started_at.get_or_insert_with(Instant::now);
+					if pool.status().ready < batch_config.max_transactions {
+						continue
+					}
+				}
+				None => return,
+			},
+			_ = Delay::new(timeout).fuse() => {
+				if batch_started_at.is_none() {
+					continue
 				}
+			}
+		}
 
-				let best_block_header = match select_chain.clone().best_chain() {
-					Err(_) => return,
-					Ok(best) => best,
-				};
+		batch_started_at = None;
 
-				let mut proposer = match env.clone().lock().init(&best_block_header) {
-					Err(_) => return,
-					Ok(p) => p,
-				};
+		if pool.status().ready == 0 {
+			log::warn!(target: "instant-seal", "No ready transactions, skipping block authorship");
+			continue
+		}
 
-				let id = match inherent_data_providers.clone().create_inherent_data() {
-					Err(_) => return,
-					Ok(id) => id,
-				};
+		let best_block_header = match select_chain.best_chain() {
+			Err(err) => {
+				log::warn!(target: "instant-seal", "{}", Error::SelectChain(format!("{:?}", err)));
+				continue
+			}
+			Ok(best) => best,
+		};
 
-				let result = proposer.propose(
-					id,
-					Default::default(),
-					Duration::from_secs(5),
-				).await;
-
-				match result {
-					Ok(block) => {
-						let (header, body) = block.deconstruct();
-						let import_params = BlockImportParams {
-							origin: BlockOrigin::Own,
-							header,
-							justification: None,
-							post_digests: Vec::new(),
-							body: Some(body),
-							finalized: true,
-							auxiliary: Vec::new(),
-							fork_choice: ForkChoiceStrategy::LongestChain,
-							allow_missing_state: false,
-						};
-
-						let res = block_import.clone()
-							.lock()
-							.import_block(import_params, HashMap::new());
-						if let Err(e) = res {
-							log::warn!("Failed to import just-constructed block: {:?}", e);
-						}
-					}
-					Err(e) => {
-						log::warn!("Failed to propose block: {:?}", e)
-					}
+		let mut proposer = match env.lock().init(&best_block_header) {
+			Err(err) => {
+				log::warn!(target: "instant-seal", "{}", Error::ProposerInit(format!("{:?}", err)));
+				continue
+			}
+			Ok(p) => p,
+		};
+
+		let id = match inherent_data_providers.create_inherent_data() {
+			Err(err) => {
+				log::warn!(target: "instant-seal", "{}", Error::InherentData(format!("{:?}", err)));
+				continue
+			}
+			Ok(id) => id,
+		};
+
+		let started_proposing_at = Instant::now();
+		let result = proposer.propose(
+			id,
+			Default::default(),
+			Duration::from_secs(5),
+		).await;
+		let proposing_duration = started_proposing_at.elapsed();
+
+		match result {
+			Ok(block) => {
+				let (header, body) = block.deconstruct();
+
+				let _ = proposed_sender.unbounded_send(ProposedBlock {
+					hash: header.hash(),
+					header: header.clone(),
+					body_len: body.len(),
+					duration: proposing_duration,
+				});
+
+				let import_params = BlockImportParams {
+					origin: BlockOrigin::Own,
+					header,
+					justification: None,
+					post_digests: Vec::new(),
+					body: Some(body),
+					finalized: instant_finalize,
+					auxiliary: Vec::new(),
+					fork_choice: ForkChoiceStrategy::LongestChain,
+					allow_missing_state: false,
 				};
+
+				match block_import.lock().import_block(import_params, HashMap::new()) {
+					Ok(ImportResult::Imported(_)) => (),
+					Ok(other) => log::warn!(
+						target: "instant-seal", "{}", Error::BlockImport(other),
+					),
+					Err(err) => log::warn!(
+						target: "instant-seal",
+						"Failed to import just-constructed block: {:?}", err,
+					),
+				}
 			}
-		}).await
+			Err(err) => log::warn!(
+				target: "instant-seal", "{}", Error::Proposal(format!("{:?}", err)),
+			),
+		};
+	}
 }
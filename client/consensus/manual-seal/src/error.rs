@@ -0,0 +1,84 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Errors that can occur while sealing or finalizing a block through the manual-seal engine.
+
+use consensus_common::ImportResult;
+
+/// Error type for this crate.
+#[derive(Debug)]
+pub enum Error {
+	/// Failed to fetch the chain to build a new block on.
+	SelectChain(String),
+	/// Failed to create a proposer for the given parent block.
+	ProposerInit(String),
+	/// Failed to create the inherent data for a new block.
+	InherentData(String),
+	/// Failed to propose a new block.
+	Proposal(String),
+	/// The block import didn't result in the block being imported.
+	BlockImport(ImportResult),
+	/// The receiving end of a oneshot channel was dropped before a reply could be sent.
+	SendError,
+	/// A block was requested without `force`, but the transaction pool had no ready transactions.
+	EmptyTransactionPool,
+	/// Any other error.
+	Other(Box<dyn std::error::Error + Send>),
+}
+
+impl Error {
+	/// A stable error code, surfaced to JSON-RPC clients as the `code` of the error object.
+	pub fn to_code(&self) -> i64 {
+		match self {
+			Error::SelectChain(_) => 100,
+			Error::ProposerInit(_) => 101,
+			Error::InherentData(_) => 102,
+			Error::Proposal(_) => 103,
+			Error::BlockImport(_) => 104,
+			Error::SendError => 105,
+			Error::EmptyTransactionPool => 106,
+			Error::Other(_) => 107,
+		}
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Error::SelectChain(err) => write!(f, "Failed to fetch best chain: {}", err),
+			Error::ProposerInit(err) => write!(f, "Failed to create proposer: {}", err),
+			Error::InherentData(err) => write!(f, "Failed to create inherent data: {}", err),
+			Error::Proposal(err) => write!(f, "Failed to propose block: {}", err),
+			Error::BlockImport(res) => write!(f, "Block was not imported: {:?}", res),
+			Error::SendError => write!(f, "Failed to send result, receiver dropped"),
+			Error::EmptyTransactionPool =>
+				write!(f, "No ready transactions, use `force` to create an empty block"),
+			Error::Other(err) => write!(f, "{}", err),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for jsonrpc_core::Error {
+	fn from(err: Error) -> jsonrpc_core::Error {
+		jsonrpc_core::Error {
+			code: jsonrpc_core::ErrorCode::ServerError(err.to_code()),
+			message: err.to_string(),
+			data: None,
+		}
+	}
+}
@@ -0,0 +1,234 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A manual sealing engine: unlike the instant-seal engine, this engine never authors a block
+//! on its own. Instead, it reacts to [`EngineCommand`]s submitted through the accompanying
+//! [`rpc::ManualSeal`] JSON-RPC interface, which lets tests and tooling deterministically drive
+//! block production (including forks) and finalization in a single-node development environment.
+
+pub mod error;
+pub mod rpc;
+
+pub use error::Error;
+pub use rpc::{ManualSeal, ManualSealApi};
+
+use consensus_common::{
+	BlockImport, Environment, Proposer, BlockImportParams, BlockOrigin,
+	ForkChoiceStrategy, ImportResult, SelectChain,
+};
+use client_api::{blockchain::HeaderBackend, Finalizer};
+use sr_primitives::generic::BlockId;
+use sr_primitives::traits::{Block as BlockT, Header as HeaderT};
+use sr_primitives::Justification;
+use futures::prelude::*;
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use serde::Serialize;
+use transaction_pool::txpool::{self, Pool as TransactionPool};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// The reply-channel carried by [`EngineCommand::SealNewBlock`].
+///
+/// `None` when the caller isn't interested in the outcome of the seal, e.g. when the command
+/// was generated internally rather than in response to an RPC call.
+pub type Sender<Hash> = Option<oneshot::Sender<Result<CreatedBlock<Hash>, Error>>>;
+
+/// The engine's notion of a successfully sealed block.
+///
+/// Only the `is_new_best` flag is lifted out of the block import's `ImportedAux`; the rest of
+/// `ImportedAux` isn't `Serialize` and isn't needed by RPC callers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedBlock<Hash> {
+	/// hash of the sealed block.
+	pub hash: Hash,
+	/// whether the sealed block became the new best block.
+	pub is_new_best: bool,
+}
+
+/// Message sent to the authorship task, asking it to seal or finalize a block.
+pub enum EngineCommand<Hash> {
+	/// Tells the engine to propose a new block
+	///
+	/// if force == true, it will create empty blocks.
+	SealNewBlock {
+		/// whether to propose an empty block in the absence of ready transactions.
+		force: bool,
+		/// the parent hash to build the new block on, defaults to the best block when `None`.
+		///
+		/// supplying a parent that isn't the current best lets callers deterministically create
+		/// a fork; the new block is imported without becoming the new best, via
+		/// `ForkChoiceStrategy::Custom(false)`.
+		parent_hash: Option<Hash>,
+		/// sends the result of the seal back to the caller, if any.
+		sender: Sender<Hash>,
+	},
+	/// Tells the engine to finalize the given block.
+	FinalizeBlock {
+		/// hash of the block to finalize.
+		hash: Hash,
+		/// justification to finalize with, if any.
+		justification: Option<Justification>,
+		/// sends the result of the finalization back to the caller, if any.
+		sender: Option<oneshot::Sender<Result<(), Error>>>,
+	},
+}
+
+/// Runs the background authorship task for the manual seal engine.
+///
+/// Unlike [`instant_seal::run_instant_seal`], this task never proposes a block on its own; it
+/// waits for an [`EngineCommand`] on `commands_stream` (typically forwarded from
+/// [`rpc::ManualSeal`]) and reports the outcome of each seal back over the command's `sender`.
+pub async fn run_manual_seal<B, BI, CB, E, A, C, S>(
+	block_import: BI,
+	env: E,
+	pool: Arc<TransactionPool<A>>,
+	mut commands_stream: S,
+	select_chain: C,
+	client: Arc<CB>,
+	inherent_data_providers: inherents::InherentDataProviders,
+	// whether newly authored/imported blocks should be marked final immediately, or left for an
+	// explicit `engine_finalizeBlock` call to decide.
+	instant_finalize: bool,
+)
+	where
+		B: BlockT + 'static,
+		BI: BlockImport<B> + 'static,
+		CB: HeaderBackend<B> + Finalizer<B> + 'static,
+		E: Environment<B> + 'static,
+		A: txpool::ChainApi + 'static,
+		C: SelectChain<B> + 'static,
+		S: Stream<Item = EngineCommand<<B as BlockT>::Hash>> + Unpin,
+{
+	let block_import = Arc::new(Mutex::new(block_import));
+	let env = Arc::new(Mutex::new(env));
+
+	while let Some(command) = commands_stream.next().await {
+		match command {
+			EngineCommand::SealNewBlock { force, parent_hash, sender } => {
+				let result = seal_one_block(
+					&block_import,
+					&env,
+					&pool,
+					&select_chain,
+					&client,
+					&inherent_data_providers,
+					force,
+					parent_hash,
+					instant_finalize,
+				).await;
+
+				if let Some(sender) = sender {
+					let _ = sender.send(result);
+				} else if let Err(err) = result {
+					log::warn!(target: "manual-seal", "Failed to seal block: {}", err);
+				}
+			}
+			EngineCommand::FinalizeBlock { hash, justification, sender } => {
+				let result = client.finalize_block(BlockId::Hash(hash), justification, true)
+					.map_err(|err| Error::Other(Box::new(err)));
+
+				if let Some(sender) = sender {
+					let _ = sender.send(result);
+				} else if let Err(err) = result {
+					log::warn!(target: "manual-seal", "Failed to finalize block: {}", err);
+				}
+			}
+		}
+	}
+}
+
+/// Proposes, then imports, a single block on top of `parent_hash` (or the current best chain
+/// when `None`).
+async fn seal_one_block<B, BI, CB, E, A, C>(
+	block_import: &Arc<Mutex<BI>>,
+	env: &Arc<Mutex<E>>,
+	pool: &Arc<TransactionPool<A>>,
+	select_chain: &C,
+	client: &Arc<CB>,
+	inherent_data_providers: &inherents::InherentDataProviders,
+	force: bool,
+	parent_hash: Option<<B as BlockT>::Hash>,
+	instant_finalize: bool,
+) -> Result<CreatedBlock<<B as BlockT>::Hash>, Error>
+	where
+		B: BlockT + 'static,
+		BI: BlockImport<B> + 'static,
+		CB: HeaderBackend<B> + Finalizer<B> + 'static,
+		E: Environment<B> + 'static,
+		A: txpool::ChainApi + 'static,
+		C: SelectChain<B> + 'static,
+{
+	if pool.status().ready == 0 && !force {
+		return Err(Error::EmptyTransactionPool);
+	}
+
+	let (parent_header, is_fork) = match parent_hash {
+		Some(hash) => {
+			let header = client.header(BlockId::Hash(hash))
+				.map_err(|err| Error::SelectChain(format!("{:?}", err)))?
+				.ok_or_else(|| Error::SelectChain(format!("Parent block {:?} not found", hash)))?;
+			(header, true)
+		}
+		None => {
+			let header = select_chain.best_chain()
+				.map_err(|err| Error::SelectChain(format!("{:?}", err)))?;
+			(header, false)
+		}
+	};
+
+	let mut proposer = env.lock().init(&parent_header)
+		.map_err(|err| Error::ProposerInit(format!("{:?}", err)))?;
+
+	let inherent_data = inherent_data_providers.create_inherent_data()
+		.map_err(|err| Error::InherentData(format!("{:?}", err)))?;
+
+	let proposal = proposer.propose(
+		inherent_data,
+		Default::default(),
+		Duration::from_secs(5),
+	).await.map_err(|err| Error::Proposal(format!("{:?}", err)))?;
+
+	let (header, body) = proposal.deconstruct();
+	let header_hash = header.hash();
+
+	let import_params = BlockImportParams {
+		origin: BlockOrigin::Own,
+		header,
+		justification: None,
+		post_digests: Vec::new(),
+		body: Some(body),
+		// a fork block never becomes the new best, so it must never be finalized either, no
+		// matter the value of `instant_finalize` — otherwise we'd finalize a block that a
+		// sibling at the same height could still outcompete.
+		finalized: instant_finalize && !is_fork,
+		auxiliary: Vec::new(),
+		fork_choice: if is_fork {
+			ForkChoiceStrategy::Custom(false)
+		} else {
+			ForkChoiceStrategy::LongestChain
+		},
+		allow_missing_state: false,
+	};
+
+	match block_import.lock().import_block(import_params, HashMap::new()) {
+		Ok(ImportResult::Imported(aux)) => Ok(CreatedBlock { hash: header_hash, is_new_best: aux.is_new_best }),
+		Ok(other) => Err(Error::BlockImport(other)),
+		Err(err) => Err(Error::Other(Box::new(
+			std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", err))
+		))),
+	}
+}
@@ -15,51 +15,107 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 //! RPC interface for the ManualSeal Engine.
-use jsonrpc_core::{Result};
+use std::{pin::Pin, future::Future};
+use jsonrpc_core::{Result, Error, ErrorCode};
 use jsonrpc_derive::rpc;
-use futures::channel::mpsc;
+use futures::channel::{mpsc, oneshot};
+use futures::{FutureExt, SinkExt};
+use serde::{de::DeserializeOwned, Serialize};
+use sr_primitives::Justification;
 
-/// The "engine" receives these messages over a channel
-pub enum EngineCommand {
-	/// Tells the engine to propose a new block
-	///
-	/// if force == true, it will create empty blocks.
-	SealNewBlock {
-		force: bool
-	},
-	/// Tells the engine to create a fork
-	///
-	/// TODO: implement CreateFork message handling
-	CreateFork
-}
+use crate::{EngineCommand, CreatedBlock};
+
+/// Future type of the [`ManualSealApi`]'s RPC methods.
+pub type FutureResult<T> = Pin<Box<dyn Future<Output = Result<T>> + Send>>;
 
 #[rpc]
-pub trait ManualSealApi {
+pub trait ManualSealApi<Hash> {
+	/// Instructs the manual-seal authorship task to propose a new block
+	///
+	/// `parent_hash`, when supplied, builds the block on top of that header instead of the
+	/// current best block, without making the result the new best (see
+	/// [`crate::EngineCommand::SealNewBlock`]).
 	#[rpc(name = "engine_createBlock")]
 	fn create_block(
 		&self,
 		force: bool,
-	) -> Result<()>;
+		parent_hash: Option<Hash>,
+	) -> FutureResult<CreatedBlock<Hash>>;
+
+	/// Instructs the manual-seal authorship task to finalize a block
+	#[rpc(name = "engine_finalizeBlock")]
+	fn finalize_block(
+		&self,
+		hash: Hash,
+		justification: Option<Justification>,
+	) -> FutureResult<bool>;
 }
 
 /// A struct that implements the [`ManualSealApi`].
-pub struct ManualSeal {
-	import_block_channel: mpsc::UnboundedSender<EngineCommand>,
+pub struct ManualSeal<Hash> {
+	import_block_channel: mpsc::UnboundedSender<EngineCommand<Hash>>,
 }
 
-impl ManualSeal {
-	/// Create new `ManualSeal` with the given reference to the client.
-	pub fn new(import_block_channel: mpsc::UnboundedSender<EngineCommand>) -> Self {
+impl<Hash> ManualSeal<Hash> {
+	/// Create new `ManualSeal` with the given reference to the block import channel.
+	pub fn new(import_block_channel: mpsc::UnboundedSender<EngineCommand<Hash>>) -> Self {
 		Self { import_block_channel }
 	}
 }
 
-impl ManualSealApi for ManualSeal {
+impl<Hash: Send + Sync + 'static + Serialize + DeserializeOwned> ManualSealApi<Hash> for ManualSeal<Hash> {
 	fn create_block(
 		&self,
 		force: bool,
-	) -> Result<()> {
-		let _ = self.import_block_channel.unbounded_send(EngineCommand::SealNewBlock { force });
-		Ok(())
+		parent_hash: Option<Hash>,
+	) -> FutureResult<CreatedBlock<Hash>> {
+		let mut sink = self.import_block_channel.clone();
+
+		async move {
+			let (sender, receiver) = oneshot::channel();
+
+			sink.send(EngineCommand::SealNewBlock { force, parent_hash, sender: Some(sender) }).await
+				.map_err(|err| Error {
+					code: ErrorCode::InternalError,
+					message: format!("Failed to send block request: {:?}", err),
+					data: None,
+				})?;
+
+			receiver.await
+				.map_err(|err| Error {
+					code: ErrorCode::InternalError,
+					message: format!("Engine dropped the sealing request: {:?}", err),
+					data: None,
+				})?
+				.map_err(Into::into)
+		}.boxed()
+	}
+
+	fn finalize_block(
+		&self,
+		hash: Hash,
+		justification: Option<Justification>,
+	) -> FutureResult<bool> {
+		let mut sink = self.import_block_channel.clone();
+
+		async move {
+			let (sender, receiver) = oneshot::channel();
+
+			sink.send(EngineCommand::FinalizeBlock { hash, justification, sender: Some(sender) }).await
+				.map_err(|err| Error {
+					code: ErrorCode::InternalError,
+					message: format!("Failed to send finalize request: {:?}", err),
+					data: None,
+				})?;
+
+			receiver.await
+				.map_err(|err| Error {
+					code: ErrorCode::InternalError,
+					message: format!("Engine dropped the finalization request: {:?}", err),
+					data: None,
+				})?
+				.map(|()| true)
+				.map_err(Into::into)
+		}.boxed()
 	}
-}
\ No newline at end of file
+}